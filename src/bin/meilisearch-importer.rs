@@ -1,5 +1,9 @@
 use clap::{App, Arg};
 use color_eyre::Report;
+use reqwest::blocking::Client;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
 use xapiary::util::glob_files;
 use xapiary::xq_document::parse_file;
 
@@ -12,13 +16,149 @@ fn setup() -> Result<(), Report> {
     Ok(())
 }
 
+/// Turn a frontmatter id (if present) or a file path into a Meilisearch-safe
+/// primary key, so re-imports of the same file upsert instead of duplicate.
+fn document_id(path: &Path, doc: &Value) -> String {
+    let raw = doc
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned());
+
+    raw.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn push_batch(
+    client: &Client,
+    url: &str,
+    index: &str,
+    key: Option<&str>,
+    batch: &mut Vec<Value>,
+    verbosity: u64,
+) -> Result<(), Report> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    let endpoint = format!("{}/indexes/{}/documents", url, index);
+    let mut req = client.post(&endpoint).body(serde_json::to_string(batch)?);
+    if let Some(key) = key {
+        req = req.bearer_auth(key);
+    }
+    let res = req.send()?;
+    let status = res.status();
+    if !status.is_success() {
+        let body = res.text().unwrap_or_default();
+        return Err(Report::msg(format!(
+            "meilisearch rejected a batch of {} docs: {} {}",
+            batch.len(),
+            status,
+            body
+        )));
+    }
+    if verbosity > 0 {
+        println!("✅ pushed {} docs: {}", batch.len(), status);
+    }
+
+    batch.clear();
+    Ok(())
+}
+
+/// Glob `pattern` and collect the document id of every file that parses
+/// cleanly, along with a count of files that didn't.
+fn collect_ids(pattern: &str, verbosity: u64) -> Result<(HashSet<String>, usize), Report> {
+    let mut ids = HashSet::new();
+    let mut failures = 0usize;
+
+    for entry in glob_files(pattern, verbosity as i8).map_err(|e| Report::msg(format!("Failed to read glob pattern: {:?}", e)))? {
+        match entry {
+            Ok(path) => {
+                if let Ok(xqdoc) = parse_file(&path) {
+                    let value = serde_json::to_value(&xqdoc)?;
+                    ids.insert(document_id(&path, &value));
+                } else {
+                    failures += 1;
+                }
+            }
+            Err(_) => failures += 1,
+        }
+    }
+
+    Ok((ids, failures))
+}
+
+/// Delete every document from the index whose id isn't in `seen` -- i.e.
+/// whose source file no longer exists on disk.
+fn prune(
+    client: &Client,
+    url: &str,
+    index: &str,
+    key: Option<&str>,
+    seen: &HashSet<String>,
+    verbosity: u64,
+) -> Result<(), Report> {
+    let limit = 1000;
+    let mut offset = 0;
+    let mut existing = HashSet::new();
+
+    loop {
+        let endpoint = format!(
+            "{}/indexes/{}/documents?limit={}&offset={}&fields=id",
+            url, index, limit, offset
+        );
+        let mut req = client.get(&endpoint);
+        if let Some(key) = key {
+            req = req.bearer_auth(key);
+        }
+        let page: Value = req.send()?.json()?;
+        let results = page
+            .get("results")
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+        if results.is_empty() {
+            break;
+        }
+
+        for doc in &results {
+            if let Some(id) = doc.get("id").and_then(|v| v.as_str()) {
+                existing.insert(id.to_string());
+            }
+        }
+
+        if results.len() < limit {
+            break;
+        }
+        offset += limit;
+    }
+
+    let stale: Vec<&String> = existing.difference(seen).collect();
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    let endpoint = format!("{}/indexes/{}/documents/delete-batch", url, index);
+    let mut req = client.post(&endpoint).body(serde_json::to_string(&stale)?);
+    if let Some(key) = key {
+        req = req.bearer_auth(key);
+    }
+    req.send()?;
+    if verbosity > 0 {
+        println!("🗑️  pruned {} stale documents", stale.len());
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Report> {
     setup()?;
 
     let cli = App::new("meilisearch-importer")
         .version("1.0")
         .author("Steve <steve@little-fluffy.cloud>")
-        .about("Read my vimdiary markdown files and import them into local Meilisearch")
+        .about("Read my vimdiary markdown files and import them into Meilisearch")
         .arg(
             Arg::with_name("v")
                 .short("v")
@@ -30,13 +170,69 @@ fn main() -> Result<(), Report> {
                 .help("the files to add")
                 .required(true),
         )
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .takes_value(true)
+                .default_value("http://127.0.0.1:7700")
+                .help("Meilisearch host to import into"),
+        )
+        .arg(
+            Arg::with_name("index")
+                .long("index")
+                .takes_value(true)
+                .default_value("notes")
+                .help("Meilisearch index to import into"),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .takes_value(true)
+                .help("Meilisearch API key, if the instance requires one"),
+        )
+        .arg(
+            Arg::with_name("batch_size")
+                .long("batch-size")
+                .takes_value(true)
+                .default_value("100")
+                .help("Number of documents to accumulate per import request"),
+        )
+        .arg(
+            Arg::with_name("prune")
+                .long("prune")
+                .requires("prune_glob")
+                .help("Delete documents from the index whose source file no longer exists"),
+        )
+        .arg(
+            Arg::with_name("prune_glob")
+                .long("prune-glob")
+                .takes_value(true)
+                .help(
+                    "Glob matching the FULL corpus root(s), used with --prune to decide what \
+                     still exists on disk. --prune deletes every indexed document outside this \
+                     set, so passing anything narrower than the whole corpus (e.g. this run's \
+                     own --globpath, if it's scoped to one subdirectory) will delete live \
+                     documents. Required by --prune for this reason.",
+                ),
+        )
         .get_matches();
 
     let verbosity = cli.occurrences_of("v");
+    let url = cli.value_of("url").unwrap().trim_end_matches('/').to_string();
+    let index = cli.value_of("index").unwrap().to_string();
+    let key = cli.value_of("key").map(|s| s.to_string());
+    let batch_size: usize = cli
+        .value_of("batch_size")
+        .unwrap()
+        .parse()
+        .expect("--batch-size must be a positive integer");
+    let prune_mode = cli.is_present("prune");
 
-    let client = reqwest::blocking::Client::new();
+    let client = Client::new();
+    let mut batch: Vec<Value> = Vec::with_capacity(batch_size);
+    let mut failures = 0usize;
 
-    // Read the markdown files and post them to local Meilisearch
+    // Read the markdown files and batch-import them into Meilisearch
     for entry in glob_files(
         cli.value_of("globpath").unwrap(),
         cli.occurrences_of("v") as i8,
@@ -44,21 +240,24 @@ fn main() -> Result<(), Report> {
     .expect("Failed to read glob pattern")
     {
         match entry {
-            // TODO convert this to iterator style using map/filter
             Ok(path) => {
-                if let Ok(mut xqdoc) = parse_file(&path) {
-                    let out = xqdoc.clone();
-                    let res = client
-                        .post("http://127.0.0.1:7700/indexes/notes/documents")
-                        .body(serde_json::to_string(&vec![xqdoc]).unwrap())
-                        .send()?;
+                if let Ok(xqdoc) = parse_file(&path) {
+                    let mut value = serde_json::to_value(&xqdoc)?;
+                    let id = document_id(&path, &value);
+                    if let Value::Object(ref mut map) = value {
+                        map.insert("id".to_string(), Value::String(id.clone()));
+                    }
+
                     if verbosity > 0 {
-                        println!(
-                            "✅ {:?} {}",
-                            res, serde_json::to_string(&vec![out]).unwrap(),
-                        );
+                        println!("✅ {} {}", path.display(), serde_json::to_string(&value)?);
+                    }
+
+                    batch.push(value);
+                    if batch.len() >= batch_size {
+                        push_batch(&client, &url, &index, key.as_deref(), &mut batch, verbosity)?;
                     }
                 } else {
+                    failures += 1;
                     eprintln!("❌ Failed to load file {}", path.display());
                 }
             }
@@ -66,6 +265,32 @@ fn main() -> Result<(), Report> {
             Err(e) => eprintln!("❌ {:?}", e),
         }
     }
+    push_batch(&client, &url, &index, key.as_deref(), &mut batch, verbosity)?;
+
+    if prune_mode {
+        if failures > 0 {
+            return Err(Report::msg(format!(
+                "refusing --prune: {} file(s) failed to parse this run, so the on-disk set is incomplete",
+                failures
+            )));
+        }
+
+        // `clap`'s `requires("prune_glob")` guarantees this is present, but
+        // the whole point of --prune-glob is that it must cover the FULL
+        // corpus, not just this run's (possibly narrower) --globpath -- so
+        // re-glob it here rather than reusing the ids of the documents
+        // imported above.
+        let prune_glob = cli.value_of("prune_glob").expect("--prune requires --prune-glob");
+        let (corpus_ids, corpus_failures) = collect_ids(prune_glob, verbosity)?;
+        if corpus_failures > 0 {
+            return Err(Report::msg(format!(
+                "refusing --prune: {} file(s) matched by --prune-glob failed to parse, so the on-disk set is incomplete",
+                corpus_failures
+            )));
+        }
+
+        prune(&client, &url, &index, key.as_deref(), &corpus_ids, verbosity)?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,100 @@
+//! Markdown + YAML-frontmatter document parsing and Xapian indexing.
+
+use crate::structure::{self, Structure};
+use color_eyre::Report;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value as YamlValue;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use xapian_rusty::{Document as XapianDocument, TermGenerator, WritableDatabase};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub filename: String,
+    pub frontmatter: BTreeMap<String, YamlValue>,
+    pub body: String,
+    // The tree-sitter walk is CPU-bound, so it happens here in parse_file
+    // (run across the rayon pool) rather than in update_index (the serial
+    // writer thread), which just applies the already-collected terms.
+    #[serde(skip)]
+    structure: Option<Structure>,
+}
+
+impl Document {
+    pub fn parse_file(path: &Path, structured: bool) -> Result<Document, Report> {
+        let contents = fs::read_to_string(path)?;
+        let (frontmatter, body) = split_frontmatter(&contents)?;
+        let structure = structured.then(|| structure::extract(&body)).transpose()?;
+
+        Ok(Document {
+            filename: path.to_string_lossy().into_owned(),
+            frontmatter,
+            body,
+            structure,
+        })
+    }
+
+    /// Stem and index the document into `db`, replacing any existing
+    /// document with the same unique term.
+    pub fn update_index(&self, db: &mut WritableDatabase, tg: &mut TermGenerator) -> Result<(), Report> {
+        let mut xdoc = XapianDocument::new()?;
+        xdoc.set_data(&serde_json::to_string(self)?)?;
+
+        tg.set_document(&mut xdoc)?;
+        tg.index_text(&self.body)?;
+
+        if let Some(tags) = self
+            .frontmatter
+            .get("tags")
+            .or_else(|| self.frontmatter.get("tag"))
+        {
+            for tag in yaml_to_strings(tags) {
+                xdoc.add_boolean_term(&format!("XTAG{}", tag.to_lowercase()))?;
+            }
+        }
+
+        if let Some(ref structure) = self.structure {
+            structure::apply(structure, &mut xdoc, tg)?;
+        }
+
+        let unique_term = self.unique_term();
+        xdoc.add_boolean_term(&unique_term)?;
+        db.replace_document(&unique_term, &mut xdoc)?;
+
+        Ok(())
+    }
+
+    fn unique_term(&self) -> String {
+        format!("Q{}", self.filename)
+    }
+}
+
+fn split_frontmatter(contents: &str) -> Result<(BTreeMap<String, YamlValue>, String), Report> {
+    let body = contents
+        .strip_prefix("---\n")
+        .ok_or_else(|| Report::msg("missing YAML frontmatter block"))?;
+    let end = body
+        .find("\n---")
+        .ok_or_else(|| Report::msg("unterminated YAML frontmatter block"))?;
+
+    let (raw_frontmatter, rest) = body.split_at(end);
+    let frontmatter = serde_yaml::from_str(raw_frontmatter)?;
+    let body = rest
+        .trim_start_matches("\n---")
+        .trim_start_matches('\n')
+        .to_string();
+
+    Ok((frontmatter, body))
+}
+
+fn yaml_to_strings(value: &YamlValue) -> Vec<String> {
+    match value {
+        YamlValue::Sequence(items) => items
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+        YamlValue::String(s) => vec![s.clone()],
+        _ => Vec::new(),
+    }
+}
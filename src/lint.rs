@@ -0,0 +1,161 @@
+//! `lint`: validate markdown frontmatter against a declared TOML schema.
+
+use color_eyre::Report;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[derive(Debug, Deserialize)]
+pub struct Schema {
+    #[serde(default)]
+    required: Vec<String>,
+    #[serde(default)]
+    allowed: Vec<String>,
+    #[serde(default)]
+    types: BTreeMap<String, String>,
+}
+
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Lint every `.md` file under `paths` against the schema at `schema_path`,
+/// printing one diagnostic per line. Returns `true` if no file failed.
+pub fn run(paths: &[String], schema_path: &Path) -> Result<bool, Report> {
+    let raw = fs::read_to_string(schema_path)?;
+    let schema: Schema = toml::from_str(&raw)?;
+
+    let mut diagnostics = Vec::new();
+    for path in paths {
+        let walker = WalkDir::new(path).into_iter().filter_entry(|e| {
+            !e.file_name()
+                .to_str()
+                .map(|s| s.starts_with('.'))
+                .unwrap_or(false)
+        });
+        for entry in walker {
+            match entry {
+                Ok(entry) => {
+                    let p = entry.path();
+                    if p.extension().and_then(|e| e.to_str()) != Some("md") {
+                        continue;
+                    }
+                    diagnostics.extend(lint_file(p, &schema));
+                }
+                Err(e) => eprintln!("❌ {:?}", e),
+            }
+        }
+    }
+
+    for d in &diagnostics {
+        println!("{}: {}", d.path.display(), d.message);
+    }
+
+    Ok(diagnostics.is_empty())
+}
+
+fn lint_file(path: &Path, schema: &Schema) -> Vec<Diagnostic> {
+    let diagnostic = |message: String| {
+        vec![Diagnostic {
+            path: path.to_path_buf(),
+            message,
+        }]
+    };
+
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return diagnostic(format!("could not read file: {}", e)),
+    };
+
+    let frontmatter = match extract_frontmatter(&contents) {
+        Some(raw) => raw,
+        None => return diagnostic("missing YAML frontmatter block".to_string()),
+    };
+
+    let parsed: Value = match serde_yaml::from_str(frontmatter) {
+        Ok(v) => v,
+        Err(e) => return diagnostic(format!("unparseable frontmatter: {}", e)),
+    };
+
+    let map = match parsed.as_mapping() {
+        Some(m) => m,
+        None => return diagnostic("frontmatter is not a mapping".to_string()),
+    };
+
+    let mut diagnostics = Vec::new();
+
+    for key in &schema.required {
+        if !map.contains_key(Value::String(key.clone())) {
+            diagnostics.push(Diagnostic {
+                path: path.to_path_buf(),
+                message: format!("missing required key '{}'", key),
+            });
+        }
+    }
+
+    for (k, v) in map {
+        let key = match k.as_str() {
+            Some(k) => k,
+            None => continue,
+        };
+
+        if !schema.allowed.is_empty()
+            && !schema.allowed.iter().any(|a| a == key)
+            && !schema.required.iter().any(|r| r == key)
+        {
+            diagnostics.push(Diagnostic {
+                path: path.to_path_buf(),
+                message: format!("unrecognized key '{}'", key),
+            });
+        }
+
+        if let Some(expected) = schema.types.get(key) {
+            if !matches_type(v, expected) {
+                diagnostics.push(Diagnostic {
+                    path: path.to_path_buf(),
+                    message: format!(
+                        "key '{}' expected type {}, got {}",
+                        key,
+                        expected,
+                        value_type_name(v)
+                    ),
+                });
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn extract_frontmatter(contents: &str) -> Option<&str> {
+    let body = contents.strip_prefix("---\n")?;
+    let end = body.find("\n---")?;
+    Some(&body[..end])
+}
+
+fn matches_type(v: &Value, expected: &str) -> bool {
+    match expected {
+        "string" => v.is_string(),
+        "integer" | "int" => v.is_i64() || v.is_u64(),
+        "number" => v.is_number(),
+        "bool" | "boolean" => v.is_bool(),
+        "array" | "list" => v.is_sequence(),
+        _ => true,
+    }
+}
+
+fn value_type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Sequence(_) => "array",
+        Value::Mapping(_) => "mapping",
+        Value::Tagged(_) => "tagged",
+    }
+}
@@ -1,8 +1,16 @@
+mod document;
 mod interactive;
+mod lint;
+mod progress;
+mod query;
+mod server;
+mod stats;
+mod structure;
 use clap::{Parser, Subcommand};
 use color_eyre::Report;
-use markdown_query::document;
+use rayon::prelude::*;
 use std::ffi::OsStr;
+use std::path::PathBuf;
 use walkdir::WalkDir;
 use xapian_rusty::{Database, Stem, TermGenerator, WritableDatabase, BRASS, DB_CREATE_OR_OPEN};
 
@@ -38,15 +46,118 @@ enum Subcommands {
     Update {
         // directory to recursively search
         paths: Vec<String>,
+
+        // Cap the parser thread pool; defaults to the number of cores
+        #[clap(short, long)]
+        jobs: Option<usize>,
+
+        // Also run the tree-sitter-markdown structural pass (headings,
+        // fenced code blocks, link targets) and index it into the
+        // H:/C:/L: prefixes
+        #[clap(long)]
+        structured: bool,
     },
 
-    // Specify a starting query for interactive query mode
+    // Run a query headlessly and print matching documents, instead of
+    // dropping into the interactive pager
     Query {
         // Query string
         query: String,
+
+        // Output format for matched documents
+        #[clap(long, default_value = "text")]
+        format: OutputFormat,
+
+        // Maximum number of results to return
+        #[clap(long, default_value = "100")]
+        limit: usize,
+    },
+
+    // Start a long-lived HTTP server answering queries against the index
+    Serve {
+        // Address to bind the HTTP server to
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+
+        // Path to a PID file; refuses to start if it names a live process
+        #[clap(long, parse(from_os_str))]
+        pid_file: Option<std::path::PathBuf>,
+
+        // Start even if the PID file names a still-live process
+        #[clap(long)]
+        force_pid: bool,
+
+        // Maximum number of results returned per request
+        #[clap(long, default_value = "100")]
+        max_results: usize,
+    },
+
+    // Report aggregate analytics over the index
+    Stats {
+        // How many of the most frequent terms/tags to report
+        #[clap(long, default_value = "10")]
+        top: usize,
+
+        // Emit the report as JSON instead of text
+        #[clap(long, default_value = "text")]
+        format: ReportFormat,
+    },
+
+    // Validate frontmatter across a markdown tree against a schema, without
+    // touching the index
+    Lint {
+        // directory to recursively search
+        paths: Vec<String>,
+
+        // Path to a TOML file declaring required/allowed keys and types
+        #[clap(long, parse(from_os_str))]
+        schema: std::path::PathBuf,
     },
 }
 
+/// Output format for the headless `query` subcommand.
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+    Jsonl,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            other => Err(format!(
+                "unknown format '{}' (expected text, json, or jsonl)",
+                other
+            )),
+        }
+    }
+}
+
+/// Output format for the `stats` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!("unknown format '{}' (expected text or json)", other)),
+        }
+    }
+}
+
 fn setup() -> Result<(), Report> {
     if std::env::var("RUST_LIB_BACKTRACE").is_err() {
         std::env::set_var("RUST_LIB_BACKTRACE", "1")
@@ -64,44 +175,120 @@ fn main() -> Result<(), Report> {
     setup()?;
 
     match cli.subcommand {
-        Some(Subcommands::Update { ref paths }) => {
+        Some(Subcommands::Update {
+            ref paths,
+            jobs,
+            structured,
+        }) => {
             let mut db = WritableDatabase::new(&db_path, BRASS, DB_CREATE_OR_OPEN)
                 .expect("Could not open db for writing");
             let mut tg = TermGenerator::new()?;
             let mut stemmer = Stem::new("en")?;
             tg.set_stemmer(&mut stemmer)?;
 
+            let jobs = jobs.unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            });
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs)
+                .build()
+                .expect("Could not build parse thread pool");
+
             for path in paths {
                 let walker = WalkDir::new(path).into_iter();
-                for entry in walker.filter_entry(|e| {
-                    !e.file_name()
-                        .to_str()
-                        .map(|s| s.starts_with('.'))
-                        .unwrap_or(false)
-                }) {
-                    match entry {
-                        Ok(path) => {
-                            let path = path.path();
-                            if path.extension().is_none() || path.extension().unwrap() != "md" {
-                                continue;
-                            }
-                            if let Ok(doc) = document::Document::parse_file(path) {
-                                doc.update_index(&mut db, &mut tg)?;
-                                if cli.verbosity > 0 {
-                                    println!("✅ {}", doc.filename);
-                                }
+                let files: Vec<PathBuf> = walker
+                    .filter_entry(|e| {
+                        !e.file_name()
+                            .to_str()
+                            .map(|s| s.starts_with('.'))
+                            .unwrap_or(false)
+                    })
+                    .filter_map(|entry| match entry {
+                        Ok(entry) => {
+                            let p = entry.path();
+                            if p.extension().and_then(OsStr::to_str) == Some("md") {
+                                Some(p.to_path_buf())
                             } else {
-                                eprintln!("❌ Failed to load file {}", path.display());
+                                None
                             }
                         }
+                        Err(e) => {
+                            eprintln!("❌ {:?}", e);
+                            None
+                        }
+                    })
+                    .collect();
 
-                        Err(e) => eprintln!("❌ {:?}", e),
+                // Parsing/stemming is CPU-bound and embarrassingly parallel; the
+                // Xapian writer isn't Sync, so it only ever touches `db` below.
+                let parsed: Vec<(PathBuf, _)> = pool.install(|| {
+                    files
+                        .par_iter()
+                        .map(|path| {
+                            (
+                                path.clone(),
+                                document::Document::parse_file(path, structured),
+                            )
+                        })
+                        .collect()
+                });
+
+                let progress = progress::Reporter::new(parsed.len(), cli.verbosity);
+                let mut ok = 0usize;
+                let mut failed = 0usize;
+                for (path, result) in parsed {
+                    match result {
+                        Ok(doc) => {
+                            doc.update_index(&mut db, &mut tg)?;
+                            ok += 1;
+                            progress.tick(ok);
+                            if cli.verbosity > 0 {
+                                println!("✅ {}", doc.filename);
+                            }
+                        }
+                        Err(_) => {
+                            failed += 1;
+                            eprintln!("❌ Failed to load file {}", path.display());
+                        }
                     }
                 }
+                progress.finish(ok, failed);
 
                 db.commit()?;
             }
         }
+        Some(Subcommands::Serve {
+            ref bind,
+            ref pid_file,
+            force_pid,
+            max_results,
+        }) => {
+            server::run(
+                &db_path,
+                server::Opts {
+                    bind: bind.clone(),
+                    pid_file: pid_file.clone(),
+                    force_pid,
+                    max_results,
+                },
+            )?;
+        }
+        Some(Subcommands::Stats { top, format }) => {
+            stats::run(
+                &db_path,
+                stats::Opts {
+                    top,
+                    json: format == ReportFormat::Json,
+                },
+            )?;
+        }
+        Some(Subcommands::Lint { ref paths, ref schema }) => {
+            if !lint::run(paths, schema)? {
+                std::process::exit(1);
+            }
+        }
         None => {
             interactive::setup_panic();
 
@@ -117,21 +304,29 @@ fn main() -> Result<(), Report> {
                 println!("{}", s);
             }
         }
-        // TODO: user passed in a starting query, use it
-        //Some(Subcommands::Query { ref query }) => {
-        Some(Subcommands::Query { query: _ }) => {
-            interactive::setup_panic();
-
+        Some(Subcommands::Query {
+            ref query,
+            format,
+            limit,
+        }) => {
             let db = Database::new_with_path(&db_path, DB_CREATE_OR_OPEN)?;
-            let iter = IntoIterator::into_iter(interactive::query(
-                db,
-                cli.verbosity,
-                String::from("less"),
-                String::from("vim"),
-            )?); // strings is moved here
-            for s in iter {
-                // next() moves a string out of the iter
-                println!("{}", s);
+            let parsed = query::parse(&db, query)?;
+            let docs = query::run(&db, &parsed, limit)?;
+
+            match format {
+                OutputFormat::Text => {
+                    for doc in &docs {
+                        println!("{}", doc.filename);
+                    }
+                }
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&docs)?);
+                }
+                OutputFormat::Jsonl => {
+                    for doc in &docs {
+                        println!("{}", serde_json::to_string(doc)?);
+                    }
+                }
             }
         }
     }
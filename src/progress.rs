@@ -0,0 +1,36 @@
+//! Progress reporting for long-running index operations.
+//!
+//! `Update` discovers a batch of files up front, parses them in parallel,
+//! then commits them one at a time from a single writer thread. `Reporter`
+//! tracks the discovered-vs-committed counts and prints a running line
+//! (gated behind `verbosity`) plus a final summary.
+
+use std::io::Write;
+
+pub struct Reporter {
+    total: usize,
+    verbosity: u8,
+}
+
+impl Reporter {
+    pub fn new(total: usize, verbosity: u8) -> Self {
+        Reporter { total, verbosity }
+    }
+
+    /// Report that `committed` of `self.total` discovered documents have
+    /// been written to the index so far.
+    pub fn tick(&self, committed: usize) {
+        if self.verbosity > 0 {
+            print!("\rindexed {} / {}", committed, self.total);
+            let _ = std::io::stdout().flush();
+        }
+    }
+
+    /// Print a final newline followed by a success/failure summary.
+    pub fn finish(&self, ok: usize, failed: usize) {
+        if self.verbosity > 0 {
+            println!();
+            println!("indexed {} of {} files ({} failed)", ok, self.total, failed);
+        }
+    }
+}
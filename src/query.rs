@@ -0,0 +1,44 @@
+//! Shared `foo AND bar AND tag:qux` query-language parsing, used by the
+//! headless `query` and `serve` subcommands.
+
+use crate::document::Document;
+use color_eyre::Report;
+use xapian_rusty::{Database, Enquire, Query, QueryParser, Stem, FLAG_BOOLEAN};
+
+// `lang`/`L` are exact boolean facet terms (`structure::apply` writes them
+// via `add_boolean_term`); `H` is a stemmed probabilistic prefix
+// (`structure::apply` writes it via `index_text_with_prefix`).
+const BOOLEAN_PREFIXES: &[(&str, &str)] = &[("tag", "XTAG"), ("lang", "C"), ("L", "L")];
+const PROBABILISTIC_PREFIXES: &[(&str, &str)] = &[("H", "H")];
+
+/// Parse `expr` against `db` using the shared query grammar.
+pub fn parse(db: &Database, expr: &str) -> Result<Query, Report> {
+    let mut qp = QueryParser::new()?;
+    let mut stemmer = Stem::new("en")?;
+    qp.set_stemmer(&mut stemmer)?;
+    qp.set_database(db)?;
+    for (field, prefix) in BOOLEAN_PREFIXES {
+        qp.add_boolean_prefix(field, prefix)?;
+    }
+    for (field, prefix) in PROBABILISTIC_PREFIXES {
+        qp.add_prefix(field, prefix)?;
+    }
+    Ok(qp.parse_query_full(expr, FLAG_BOOLEAN)?)
+}
+
+/// Run `query` against `db`, returning up to `max_results` matching
+/// documents in rank order.
+pub fn run(db: &Database, query: &Query, max_results: usize) -> Result<Vec<Document>, Report> {
+    let mut enquire = Enquire::new(db)?;
+    enquire.set_query(query)?;
+    let mset = enquire.get_mset(0, max_results)?;
+
+    let mut docs = Vec::new();
+    for result in mset.iter() {
+        let xdoc = result.get_document()?;
+        if let Ok(doc) = serde_json::from_str::<Document>(&xdoc.get_data()?) {
+            docs.push(doc);
+        }
+    }
+    Ok(docs)
+}
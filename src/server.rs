@@ -0,0 +1,116 @@
+//! `serve`: a long-lived HTTP daemon answering queries against the index.
+
+use crate::query;
+use color_eyre::Report;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_http::{Response, Server};
+use xapian_rusty::{Database, DB_CREATE_OR_OPEN};
+
+pub struct Opts {
+    pub bind: String,
+    pub pid_file: Option<PathBuf>,
+    pub force_pid: bool,
+    pub max_results: usize,
+}
+
+/// Start serving `db_path` over HTTP, managing the PID file (if any) for
+/// the lifetime of the server.
+pub fn run(db_path: &str, opts: Opts) -> Result<(), Report> {
+    if let Some(ref pid_file) = opts.pid_file {
+        claim_pid_file(pid_file, opts.force_pid)?;
+    }
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let handler_shutdown = shutdown.clone();
+    ctrlc::set_handler(move || handler_shutdown.store(true, Ordering::SeqCst))
+        .map_err(|e| Report::msg(format!("serve: could not install signal handler: {}", e)))?;
+
+    let result = serve(db_path, &opts, &shutdown);
+
+    if let Some(ref pid_file) = opts.pid_file {
+        let _ = fs::remove_file(pid_file);
+    }
+
+    result
+}
+
+fn pid_is_live(pid: u32) -> bool {
+    Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+fn claim_pid_file(pid_file: &Path, force: bool) -> Result<(), Report> {
+    if let Ok(contents) = fs::read_to_string(pid_file) {
+        if let Ok(pid) = contents.trim().parse::<u32>() {
+            if !force && pid_is_live(pid) {
+                return Err(Report::msg(format!(
+                    "serve: {} names live pid {}; pass --force-pid to start anyway",
+                    pid_file.display(),
+                    pid
+                )));
+            }
+        }
+    }
+    fs::write(pid_file, std::process::id().to_string())?;
+    Ok(())
+}
+
+fn serve(db_path: &str, opts: &Opts, shutdown: &AtomicBool) -> Result<(), Report> {
+    let db = Database::new_with_path(db_path, DB_CREATE_OR_OPEN)?;
+    let server = Server::http(&opts.bind)
+        .map_err(|e| Report::msg(format!("serve: could not bind {}: {}", opts.bind, e)))?;
+
+    println!("serving {} on http://{}", db_path, opts.bind);
+
+    // Poll with a short timeout rather than blocking forever, so a SIGINT/
+    // SIGTERM flips `shutdown` and we fall out to let `run()` clean up the
+    // PID file instead of leaving it orphaned.
+    while !shutdown.load(Ordering::SeqCst) {
+        let request = match server.recv_timeout(Duration::from_millis(200)) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(e) => return Err(Report::msg(format!("serve: error receiving request: {}", e))),
+        };
+
+        let (status, body) = handle(&db, request.url(), opts.max_results);
+        let response = Response::from_string(body).with_status_code(status);
+        let _ = request.respond(response);
+    }
+
+    println!("shutting down");
+    Ok(())
+}
+
+fn handle(db: &Database, url: &str, max_results: usize) -> (u16, String) {
+    let query_str = match search_query_param(url) {
+        Some(q) => q,
+        None => return (400, r#"{"error":"expected GET /search?q=<expr>"}"#.to_string()),
+    };
+
+    let parsed = match query::parse(db, &query_str) {
+        Ok(q) => q,
+        Err(e) => return (400, format!(r#"{{"error":"{}"}}"#, e)),
+    };
+
+    match query::run(db, &parsed, max_results) {
+        Ok(docs) => (
+            200,
+            serde_json::to_string(&docs).unwrap_or_else(|_| "[]".to_string()),
+        ),
+        Err(e) => (500, format!(r#"{{"error":"{}"}}"#, e)),
+    }
+}
+
+fn search_query_param(url: &str) -> Option<String> {
+    let (path, query) = url.split_once('?')?;
+    if path != "/search" {
+        return None;
+    }
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == "q").then(|| urlencoding::decode(v).ok().map(|s| s.into_owned()))?
+    })
+}
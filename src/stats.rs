@@ -0,0 +1,141 @@
+//! `stats`: read-only index analytics.
+
+use color_eyre::Report;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use xapian_rusty::{Database, DB_CREATE_OR_OPEN};
+
+pub struct Opts {
+    pub top: usize,
+    pub json: bool,
+}
+
+// `(term prefix, facet name)`. Plain prose terms vastly outnumber facet
+// values, so lumping everything into one top-N ranking buries the facet
+// values behind common words; bucket by the prefixes document::update_index/
+// structure::apply actually write and rank each bucket separately. `Q` (the
+// per-document unique term) is excluded entirely -- it's indexing
+// bookkeeping, not a facet.
+const FACET_PREFIXES: &[(&str, &str)] = &[("XTAG", "tag"), ("C", "lang"), ("H", "heading"), ("L", "link")];
+
+fn facet_of(term: &str) -> Option<(&'static str, String)> {
+    FACET_PREFIXES
+        .iter()
+        .find_map(|(prefix, name)| term.strip_prefix(prefix).map(|rest| (*name, rest.to_string())))
+}
+
+fn top_n(freq: BTreeMap<String, u32>, n: usize) -> Vec<(String, u32)> {
+    let mut ranked: Vec<(String, u32)> = freq.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1));
+    ranked.truncate(n);
+    ranked
+}
+
+pub fn run(db_path: &str, opts: Opts) -> Result<(), Report> {
+    let db = Database::new_with_path(db_path, DB_CREATE_OR_OPEN)?;
+
+    let doc_count = db.get_doccount()?;
+
+    let mut plain_freq: BTreeMap<String, u32> = BTreeMap::new();
+    let mut facet_freq: BTreeMap<&'static str, BTreeMap<String, u32>> = BTreeMap::new();
+    let mut distinct_terms = 0usize;
+    let mut term_iter = db.allterms_begin()?;
+    let term_end = db.allterms_end()?;
+    while term_iter != term_end {
+        let term = term_iter.get_termname()?;
+        let tf = db.get_termfreq(&term)?;
+        distinct_terms += 1;
+
+        if term.starts_with('Q') {
+            // Per-document unique term, not a content word or facet.
+        } else if let Some((facet, value)) = facet_of(&term) {
+            facet_freq.entry(facet).or_default().insert(value, tf);
+        } else {
+            plain_freq.insert(term, tf);
+        }
+
+        term_iter.next()?;
+    }
+
+    let mut total_len = 0u64;
+    let mut max_len = 0u32;
+    let mut live_docs = 0u64;
+    let mut frontmatter_keys: BTreeMap<String, u32> = BTreeMap::new();
+
+    // Docids aren't contiguous: deletions retire an id rather than reusing
+    // it, so `1..=doc_count` can both skip live high-numbered documents and
+    // waste lookups on gaps. Every live document posts to the empty term,
+    // so walking its postlist enumerates exactly the live docids.
+    let mut docid_iter = db.postlist_begin("")?;
+    let docid_end = db.postlist_end("")?;
+    while docid_iter != docid_end {
+        let docid = docid_iter.get_docid()?;
+
+        if let Ok(len) = db.get_doclength(docid) {
+            live_docs += 1;
+            total_len += len as u64;
+            max_len = max_len.max(len);
+        }
+
+        if let Ok(xdoc) = db.get_document(docid) {
+            if let Ok(data) = xdoc.get_data() {
+                if let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(&data) {
+                    if let Some(Value::Object(frontmatter)) = fields.get("frontmatter") {
+                        for key in frontmatter.keys() {
+                            *frontmatter_keys.entry(key.clone()).or_insert(0) += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        docid_iter.next()?;
+    }
+
+    let top_terms = top_n(plain_freq, opts.top);
+    let top_facets: BTreeMap<&'static str, Vec<(String, u32)>> = facet_freq
+        .into_iter()
+        .map(|(facet, freq)| (facet, top_n(freq, opts.top)))
+        .collect();
+
+    let avg_len = if live_docs > 0 {
+        total_len as f64 / live_docs as f64
+    } else {
+        0.0
+    };
+
+    if opts.json {
+        let report = serde_json::json!({
+            "documents": doc_count,
+            "distinct_terms": distinct_terms,
+            "top_terms": top_terms,
+            "top_facets": top_facets,
+            "avg_doc_length": avg_len,
+            "max_doc_length": max_len,
+            "frontmatter_keys": frontmatter_keys,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("documents:      {}", doc_count);
+    println!("distinct terms: {}", distinct_terms);
+    println!("avg doc length: {:.1}", avg_len);
+    println!("max doc length: {}", max_len);
+    println!("top {} terms:", opts.top);
+    for (term, freq) in &top_terms {
+        println!("  {:<24} {}", term, freq);
+    }
+    for (facet, values) in &top_facets {
+        println!("top {} {} values:", opts.top, facet);
+        for (value, freq) in values {
+            println!("  {:<24} {}", value, freq);
+        }
+    }
+    println!("frontmatter keys seen:");
+    for (key, count) in &frontmatter_keys {
+        println!("  {:<24} {}", key, count);
+    }
+
+    Ok(())
+}
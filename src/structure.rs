@@ -0,0 +1,108 @@
+//! Tree-sitter-markdown structural extraction for faceted indexing.
+
+use color_eyre::Report;
+use tree_sitter::{Node, Parser, Tree, TreeCursor};
+use xapian_rusty::{Document as XapianDocument, TermGenerator};
+
+/// Headings, fenced-code-block languages, and link targets pulled out of a
+/// document body. Collecting these is the CPU-bound tree-sitter walk, so it
+/// happens in `extract` (called from `parse_file`, fanned out across the
+/// rayon pool); turning them into Xapian terms in `apply` is cheap enough
+/// to stay on the serial writer thread in `update_index`.
+#[derive(Debug, Default, Clone)]
+pub struct Structure {
+    headings: Vec<String>,
+    code_langs: Vec<String>,
+    links: Vec<String>,
+}
+
+/// Walk `body` with tree-sitter-markdown and collect its headings, fenced
+/// code block languages, and link targets.
+pub fn extract(body: &str) -> Result<Structure, Report> {
+    let tree = parse(body)?;
+    let bytes = body.as_bytes();
+
+    let mut structure = Structure::default();
+    let mut cursor = tree.walk();
+    walk(&mut cursor, bytes, &mut structure);
+    Ok(structure)
+}
+
+/// Index a previously-extracted `Structure` into `xdoc`/`tg` under the
+/// `H:`/`C:`/`L:` prefixes.
+pub fn apply(structure: &Structure, xdoc: &mut XapianDocument, tg: &mut TermGenerator) -> Result<(), Report> {
+    for heading in &structure.headings {
+        tg.index_text_with_prefix(heading, "H")?;
+    }
+    for lang in &structure.code_langs {
+        xdoc.add_boolean_term(&format!("C{}", lang))?;
+    }
+    for link in &structure.links {
+        xdoc.add_boolean_term(&format!("L{}", link))?;
+    }
+    Ok(())
+}
+
+fn parse(body: &str) -> Result<Tree, Report> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(tree_sitter_md::language())
+        .map_err(|e| Report::msg(format!("could not load tree-sitter-markdown grammar: {}", e)))?;
+    parser
+        .parse(body, None)
+        .ok_or_else(|| Report::msg("tree-sitter-markdown failed to parse document body"))
+}
+
+fn walk(cursor: &mut TreeCursor, bytes: &[u8], structure: &mut Structure) {
+    visit(cursor.node(), bytes, structure);
+
+    if cursor.goto_first_child() {
+        loop {
+            walk(cursor, bytes, structure);
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+        cursor.goto_parent();
+    }
+}
+
+fn visit(node: Node, bytes: &[u8], structure: &mut Structure) {
+    match node.kind() {
+        "atx_heading" | "setext_heading" => collect_heading(node, bytes, structure),
+        "fenced_code_block" => collect_code_block(node, bytes, structure),
+        "inline_link" | "link" => collect_link(node, bytes, structure),
+        _ => {}
+    }
+}
+
+fn collect_heading(node: Node, bytes: &[u8], structure: &mut Structure) {
+    let content = node
+        .child_by_field_name("heading_content")
+        .or_else(|| node.named_child(0));
+
+    if let Some(text) = content.and_then(|c| c.utf8_text(bytes).ok()) {
+        structure.headings.push(text.trim().to_string());
+    }
+}
+
+fn collect_code_block(node: Node, bytes: &[u8], structure: &mut Structure) {
+    let lang = node
+        .child_by_field_name("info_string")
+        .and_then(|info| info.utf8_text(bytes).ok())
+        .and_then(|info| info.split_whitespace().next())
+        .map(|lang| lang.to_lowercase());
+
+    if let Some(lang) = lang.filter(|lang| !lang.is_empty()) {
+        structure.code_langs.push(lang);
+    }
+}
+
+fn collect_link(node: Node, bytes: &[u8], structure: &mut Structure) {
+    if let Some(url) = node
+        .child_by_field_name("link_destination")
+        .and_then(|dest| dest.utf8_text(bytes).ok())
+    {
+        structure.links.push(url.to_string());
+    }
+}